@@ -3,12 +3,22 @@ use latch::LatchProbe;
 use registry::{in_worker, Registry};
 use std::any::Any;
 use std::marker::PhantomData;
+use std::mem;
 use std::sync::Arc;
 use std::sync::Mutex;
 use tlv;
 use unwind;
 
-struct ActiveFlexScope {
+// BLOCKED: `spawn`/`spawn_collect` below still thread `data.tlv` through to
+// `HeapJob::new` as a raw `usize` (see the `tlv` field on `ActiveFlexScope`),
+// not as a strongly-typed context installed via `tlv::set_with`/`get::<T>()`
+// (chunk0-3). `HeapJob` isn't part of this tree, and adopting the typed API
+// here would mean threading a concrete `T` through `FlexScope` itself, which
+// needs `HeapJob::new`'s actual signature to do correctly. The typed
+// `tlv::set_with`/`get::<T>()` API exists and is tested in `tlv.rs`, but this
+// file doesn't use it yet.
+
+struct ActiveFlexScope<C> {
     /// thread registry where `scope()` was executed.
     registry: Arc<Registry>,
 
@@ -22,14 +32,28 @@ struct ActiveFlexScope {
 
     /// The TLV at the scope's creation. Used to set the TLV for spawned jobs.
     tlv: usize,
+
+    /// Results pushed by `spawn_collect`.
+    results: Vec<C>,
 }
 
-pub struct FlexScope<'scope> {
-    data: Mutex<Option<ActiveFlexScope>>,
+/// A [`FlexScope`] whose [`spawn_collect`](FlexScopeCollect::spawn_collect)
+/// calls collect results of type `C`. `FlexScope<'scope>` is a type alias
+/// for `FlexScopeCollect<'scope, ()>`, so callers that only use
+/// [`spawn`](FlexScopeCollect::spawn)/[`activate`](FlexScopeCollect::activate)
+/// can keep writing `FlexScope::new()` unchanged; use `FlexScopeCollect`
+/// directly when `C` isn't `()`.
+pub struct FlexScopeCollect<'scope, C> {
+    data: Mutex<Option<ActiveFlexScope<C>>>,
     marker: PhantomData<fn(&'scope ()) -> &'scope ()>,
 }
 
-impl<'scope> FlexScope<'scope> {
+/// A `FlexScope` that doesn't collect any results from `spawn_collect`
+/// (there's nothing to call it with `C = ()` for, but `spawn`/`activate`
+/// work exactly as before).
+pub type FlexScope<'scope> = FlexScopeCollect<'scope, ()>;
+
+impl<'scope, C: Send + 'scope> FlexScopeCollect<'scope, C> {
     pub fn new() -> Self {
         Self {
             data: Mutex::new(None),
@@ -38,8 +62,19 @@ impl<'scope> FlexScope<'scope> {
     }
 
     pub fn activate<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.activate_impl(f).0
+    }
+
+    /// Like [`activate`](FlexScopeCollect::activate), but also returns the
+    /// results pushed by any [`spawn_collect`](FlexScopeCollect::spawn_collect)
+    /// calls made during this activation.
+    pub fn activate_collect<R>(&self, f: impl FnOnce() -> R) -> (R, Vec<C>) {
+        self.activate_impl(f)
+    }
+
+    fn activate_impl<R>(&self, f: impl FnOnce() -> R) -> (R, Vec<C>) {
         // Activate the scope
-        let tlv = tlv::get();
+        let tlv = tlv::get_raw();
         {
             let mut data = self.data.lock().unwrap();
             assert!(data.is_none(), "scope was already activated");
@@ -49,6 +84,7 @@ impl<'scope> FlexScope<'scope> {
                 panic: None,
                 terminated: false,
                 tlv,
+                results: Vec::new(),
             })
         }
 
@@ -61,23 +97,25 @@ impl<'scope> FlexScope<'scope> {
         });
 
         // Restore the TLV if we ran some jobs while waiting
-        tlv::set(tlv);
+        tlv::set_raw(tlv);
 
-        let panic = {
+        let (panic, results) = {
             let mut data = self.data.lock().unwrap();
-            let panic = data.as_mut().unwrap().panic.take();
+            let active = data.as_mut().unwrap();
+            let panic = active.panic.take();
+            let results = mem::take(&mut active.results);
 
             // Deactivate the scope
             *data = None;
 
-            panic
+            (panic, results)
         };
 
         if let Some(panic) = panic {
             unwind::resume_unwinding(panic);
         }
 
-        result.unwrap()
+        (result.unwrap(), results)
     }
 
     pub fn spawn(&self, f: impl FnOnce() + Send + 'scope) {
@@ -99,6 +137,32 @@ impl<'scope> FlexScope<'scope> {
         data.registry.inject_or_push(job_ref);
     }
 
+    /// Like [`spawn`](FlexScopeCollect::spawn), but `f`'s return value is kept
+    /// and handed back from
+    /// [`activate_collect`](FlexScopeCollect::activate_collect) instead of
+    /// being discarded.
+    pub fn spawn_collect(&self, f: impl FnOnce() -> C + Send + 'scope) {
+        let mut data = self.data.lock().unwrap();
+        let data = data.as_mut().expect("the scope is not active");
+        assert!(!data.terminated, "the scope is terminated");
+        assert!(data.active_jobs != std::usize::MAX);
+        data.active_jobs += 1;
+
+        let job_ref = unsafe {
+            Box::new(HeapJob::new(data.tlv, move || {
+                // Store the result inside the job, before `execute_job` drops
+                // the job count, so the owner can't deactivate the scope first.
+                self.execute_job(move || {
+                    let result = f();
+                    self.data.lock().unwrap().as_mut().unwrap().results.push(result);
+                });
+            }))
+            .as_job_ref()
+        };
+
+        data.registry.inject_or_push(job_ref);
+    }
+
     fn execute_job<R>(&self, f: impl FnOnce() -> R) -> Option<R> {
         let result = unwind::halt_unwinding(f);
         let mut data = self.data.lock().unwrap();
@@ -110,14 +174,59 @@ impl<'scope> FlexScope<'scope> {
             data.terminated = true;
         }
         result.map(|r| Some(r)).unwrap_or_else(|panic| {
-            data.panic = Some(panic);
+            // The first captured panic wins; later ones are dropped.
+            if data.panic.is_none() {
+                data.panic = Some(panic);
+            }
             None
         })
     }
 }
 
-impl<'scope> LatchProbe for FlexScope<'scope> {
+impl<'scope, C: Send + 'scope> LatchProbe for FlexScopeCollect<'scope, C> {
     fn probe(&self) -> bool {
         self.data.lock().unwrap().as_ref().unwrap().active_jobs == 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ThreadPoolBuilder;
+
+    #[test]
+    fn plain_scope_infers_without_annotation() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        pool.install(|| {
+            // No type annotation needed: `FlexScope<'_>` is `FlexScopeCollect<'_, ()>`.
+            let scope = FlexScope::new();
+            let result = scope.activate(|| {
+                scope.spawn(|| {});
+                1 + 1
+            });
+            assert_eq!(result, 2);
+        });
+    }
+
+    #[test]
+    fn spawn_collect_gathers_borrowed_scope_results() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        pool.install(|| {
+            let numbers = vec![1, 2, 3, 4];
+            let scope: FlexScopeCollect<'_, i32> = FlexScopeCollect::new();
+
+            // `n` borrows `numbers`, which lives only for `'scope`: this is
+            // exactly the case `Box<dyn Any + Send>` (which requires
+            // `'static`) couldn't support.
+            let ((), doubled) = scope.activate_collect(|| {
+                for n in &numbers {
+                    scope.spawn_collect(move || n * 2);
+                }
+            });
+
+            let mut doubled = doubled;
+            doubled.sort();
+            assert_eq!(doubled, vec![2, 4, 6, 8]);
+        });
+    }
+}
@@ -2,6 +2,21 @@ use jobserver_crate::{Client, HelperThread, Acquired};
 use std::sync::{Condvar, Arc, Mutex, Weak};
 use std::mem;
 
+// BLOCKED: wiring this `Proxy` into the `Registry`/`Sleep`/`WorkerThread`
+// loop (chunk0-1) needs edits to `registry.rs` and `sleep.rs`, neither of
+// which exist in this tree, so none of it is implemented yet. No worker
+// calls `acquire_token`/`return_token`, and nothing calls `Proxy::from_env`
+// or `Proxy::disabled` when building the global registry. What follows is
+// only the contract that wiring must satisfy once those files land:
+//
+// Worker 0 holds the process's implicit token unconditionally and never
+// calls `acquire_token`/`return_token`. Every other worker acquires a
+// token before running a `JobRef` pulled off its deque, and returns its
+// token before parking in `Sleep`. This keeps the implicit token in the
+// pool at all times, so the pool always makes forward progress even if
+// every other token is handed back to the server while jobs remain
+// queued.
+
 #[derive(Default)]
 pub struct LockedProxyData {
     /// The number of free thread tokens, this may include the implicit token given to the process
@@ -83,6 +98,11 @@ pub struct Proxy {
 
 lazy_static! {
     // We can only call `from_env` once per process
+    //
+    // `jobserver_crate::Client::from_env` already parses `MAKEFLAGS` for
+    // both the classic `--jobserver-fds=R,W` pipe/semaphore form and the
+    // GNU Make 4.4+ `--jobserver-auth=fifo:PATH` form and opens whichever
+    // one is present, so no separate FIFO handling is needed here.
     static ref GLOBAL_CLIENT: Option<Client> = unsafe { Client::from_env() };
 
     // We only want one Proxy to exists at a time
@@ -120,6 +140,14 @@ impl Proxy {
         })
     }
 
+    /// Returns `true` if this proxy is backed by a real jobserver, i.e.
+    /// there are tokens to gate on beyond the process's implicit one.
+    /// Workers other than worker 0 can use this to skip the gating dance
+    /// entirely when the pool wasn't spawned under a jobserver.
+    pub fn is_enabled(&self) -> bool {
+        self.thread.is_some()
+    }
+
     pub fn return_token(&self) {
         if self.thread.is_some() {
             self.data.lock.lock().unwrap().return_token(&self.data.cond_var);
@@ -1,21 +1,85 @@
-//! Allows access to the Rayon's thread local value
-//! which is preserved when moving jobs across threads
+//! Allows access to Rayon's thread-local context value, which is preserved
+//! when a job moves across threads.
 
 use std::cell::Cell;
 
-thread_local!(pub(crate) static TLV: Cell<usize> = Cell::new(0));
+thread_local!(static TLV: Cell<usize> = Cell::new(0));
 
-/// Gives access to the thread-local value inside the closure.
-pub fn with<F: FnOnce(&Cell<usize>) -> R, R>(f: F) -> R {
-    TLV.with(f)
+/// Returns the raw thread-local context value, for threading a job's
+/// context through as it's captured on one thread and run on another.
+#[inline]
+pub(crate) fn get_raw() -> usize {
+    TLV.with(|tlv| tlv.get())
 }
 
-/// Sets the current thread-local value
-pub(crate) fn set(value: usize) {
+/// Installs the raw thread-local context value, for restoring a job's
+/// context on the thread that ends up executing it.
+#[inline]
+pub(crate) fn set_raw(value: usize) {
     TLV.with(|tlv| tlv.set(value));
 }
 
-/// Returns the current thread-local value
-pub(crate) fn get() -> usize {
-    TLV.with(|tlv| tlv.get())
+/// Restores the previous thread-local context when dropped, including on
+/// unwind. Used internally by [`set_with`].
+pub struct TlvGuard {
+    previous: usize,
+}
+
+impl Drop for TlvGuard {
+    fn drop(&mut self) {
+        TLV.with(|tlv| tlv.set(self.previous));
+    }
+}
+
+/// Installs `value` as the thread-local context for the duration of `f`,
+/// restoring the previous context when `f` returns or unwinds.
+pub fn set_with<T, F: FnOnce() -> R, R>(value: &T, f: F) -> R {
+    let _guard = TlvGuard {
+        previous: TLV.with(|tlv| tlv.replace(value as *const T as usize)),
+    };
+    f()
+}
+
+/// Returns the current thread-local context, reinterpreted as `&T`, or
+/// `None` if nothing is installed.
+///
+/// # Safety
+/// The caller must ensure `T` matches the type installed by the innermost
+/// enclosing [`set_with`] on this thread, and must not retain or use the
+/// returned reference beyond that `set_with` call's dynamic extent: the
+/// pointee is only guaranteed to be alive for as long as `set_with`'s
+/// caller keeps the original value alive, not for `'static`.
+#[inline]
+pub unsafe fn get<T>() -> Option<&'static T> {
+    let ptr = get_raw();
+    if ptr == 0 {
+        None
+    } else {
+        Some(&*(ptr as *const T))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_with_round_trips_and_restores() {
+        assert_eq!(get_raw(), 0);
+
+        let outer = 1u32;
+        set_with(&outer, || {
+            assert_eq!(unsafe { get::<u32>() }, Some(&1));
+
+            let inner = 2u32;
+            set_with(&inner, || {
+                assert_eq!(unsafe { get::<u32>() }, Some(&2));
+            });
+
+            // The outer value is restored once the inner `set_with` returns.
+            assert_eq!(unsafe { get::<u32>() }, Some(&1));
+        });
+
+        assert_eq!(get_raw(), 0);
+    }
 }
@@ -58,6 +58,29 @@ impl<T> WorkerLocal<T> {
         self.locals.into_iter().map(|c| c.0).collect()
     }
 
+    /// Returns an iterator over the worker-local value for each thread,
+    /// without consuming the `WorkerLocal`.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.locals.iter().map(|c| &c.0)
+    }
+
+    /// Folds the worker-local values into a single value by repeatedly
+    /// applying `f`, starting from `init`.
+    #[inline]
+    pub fn fold<A>(self, init: A, mut f: impl FnMut(A, T) -> A) -> A {
+        self.into_inner().into_iter().fold(init, |a, t| f(a, t))
+    }
+
+    /// Reduces the worker-local values into a single value by repeatedly
+    /// applying `f`, or returns `None` if there are no worker threads.
+    #[inline]
+    pub fn reduce(self, mut f: impl FnMut(T, T) -> T) -> Option<T> {
+        let mut iter = self.into_inner().into_iter();
+        let first = iter.next()?;
+        Some(iter.fold(first, |a, t| f(a, t)))
+    }
+
     fn current(&self) -> &T {
         unsafe {
             let idx = thread_check(&self.registry);
@@ -86,4 +109,28 @@ impl<T> Deref for WorkerLocal<T> {
     fn deref(&self) -> &T {
         self.current()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ThreadPoolBuilder;
+
+    #[test]
+    fn fold_reduce_iter() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        pool.install(|| {
+            let locals = WorkerLocal::new(|i| i);
+
+            let via_iter: usize = locals.iter().sum();
+            assert_eq!(via_iter, 0 + 1 + 2 + 3);
+
+            let reduced = locals.reduce(|a, b| a + b);
+            assert_eq!(reduced, Some(via_iter));
+
+            // `reduce` consumed `locals`, so fold over a fresh one.
+            let folded = WorkerLocal::new(|i| i).fold(10, |a, b| a + b);
+            assert_eq!(folded, 10 + via_iter);
+        });
+    }
 }
\ No newline at end of file